@@ -0,0 +1,87 @@
+//! Benchmarks comparing the synchronous filter fast path (no boxed future per request) against the
+//! asynchronous filter path (one `Box::pin` per request).
+//!
+//! On the synchronous path ([All] / [SyncFilter]) the whole middleware body runs inline and only
+//! the downstream future is driven, so no future is heap allocated per request. On the asynchronous
+//! path the body is wrapped in a fresh boxed future on every call, which is a heap allocation per
+//! request that shows up as a measurable overhead here.
+
+use api_version::{ApiVersionFilter, ApiVersionLayer, All, SyncApiVersionFilter, SyncFilter};
+use axum::{
+    body::Body,
+    http::{Request, Uri},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use criterion::{criterion_group, criterion_main, Criterion};
+use tower::{Layer, Service};
+
+/// An asynchronous filter equivalent to [All], forcing the boxed path.
+#[derive(Clone)]
+struct AsyncAll;
+
+impl ApiVersionFilter for AsyncAll {
+    async fn filter(&self, _uri: &Uri) -> bool {
+        true
+    }
+}
+
+/// A synchronous filter driven through [SyncFilter], taking the unboxed fast path.
+#[derive(Clone)]
+struct SyncAll;
+
+impl SyncApiVersionFilter for SyncAll {
+    fn filter(&self, _uri: &Uri) -> bool {
+        true
+    }
+}
+
+fn app<F>(filter: F) -> impl Service<Request<Body>, Response = Response, Error = std::convert::Infallible>
+where
+    F: ApiVersionFilter,
+{
+    let router = Router::new().route("/v0/test", get(ok));
+    ApiVersionLayer::new([0], filter).unwrap().layer(router)
+}
+
+async fn ok() -> impl IntoResponse {
+    "ok"
+}
+
+fn bench(c: &mut Criterion) {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+
+    let mut sync = app(All);
+    c.bench_function("sync_filter", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let request = Request::builder().uri("/test").body(Body::empty()).unwrap();
+                sync.call(request).await.unwrap()
+            })
+        })
+    });
+
+    let mut sync_wrapped = app(SyncFilter(SyncAll));
+    c.bench_function("sync_filter_adapter", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let request = Request::builder().uri("/test").body(Body::empty()).unwrap();
+                sync_wrapped.call(request).await.unwrap()
+            })
+        })
+    });
+
+    let mut boxed = app(AsyncAll);
+    c.bench_function("async_filter", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let request = Request::builder().uri("/test").body(Body::empty()).unwrap();
+                boxed.call(request).await.unwrap()
+            })
+        })
+    });
+}
+
+criterion_group!(benches, bench);
+criterion_main!(benches);
@@ -2,19 +2,23 @@
 
 use axum::{
     extract::Request,
-    http::{uri::PathAndQuery, HeaderName, HeaderValue, StatusCode, Uri},
+    http::{header::ACCEPT, uri::PathAndQuery, HeaderName, HeaderValue, StatusCode, Uri},
     response::{IntoResponse, Response},
-    RequestExt,
-};
-use axum_extra::{
-    headers::{self, Header},
-    TypedHeader,
+    Json,
 };
+use axum_extra::headers::{self, Header};
 use futures::future::BoxFuture;
+use pin_project_lite::pin_project;
 use regex::Regex;
+use semver::{Version, VersionReq};
+use serde::Serialize;
+use time::Date;
 use std::{
+    collections::HashMap,
     fmt::Debug,
     future::Future,
+    ops::RangeInclusive,
+    pin::Pin,
     sync::LazyLock,
     task::{Context, Poll},
 };
@@ -41,48 +45,540 @@ macro_rules! api_version {
 }
 
 /// Axum middleware to rewrite a request such that a version prefix is added to the path. This is
-/// based on a set of versions and an optional `"x-api-version"` custom HTTP header: if no such
-/// header is present, the highest version is used. Yet this only applies to requests the URIs of
-/// which pass a filter; others are not rewritten.
+/// based on a set of per-route supported versions and the API version extracted from the request
+/// via one or more [VersionSource]s (by default the `"x-api-version"` header): if no source yields
+/// a version, the highest version is used. Yet this only applies to requests the URIs of which
+/// pass a filter; others are not rewritten.
+///
+/// Supported versions are kept per path prefix in a prefix trie, so different parts of the API can
+/// support different version ranges; the incoming path is matched by longest prefix to find the
+/// applicable range. Use [new](Self::new) or the [api_version] macro for a single range applied to
+/// all paths, or [builder](Self::builder) to register `(prefix, range)` entries.
+///
+/// Two modes of version resolution are supported: the flat `u16` mode resolves by exact membership,
+/// whereas the semantic versioning mode (see [semver](Self::semver) and
+/// [route_semver](ApiVersionLayerBuilder::route_semver)) resolves by compatibility, letting the
+/// client send a full version or a `VersionReq`-style requirement such as `"^1.2"` and picking the
+/// highest supported version that satisfies it. In semver mode only the major component is used as
+/// path prefix (`"/v1"`), while the precisely resolved version is remembered in the request
+/// extensions as a [ResolvedVersion].
 ///
 /// Requests for the readiness probe `"/"` are not rewritten.
 ///
-/// Paths must not start with a version prefix, e.g. `"/v0"`.
+/// Paths must not start with a version prefix, e.g. `"/v0"`, unless [VersionSource::Path] is
+/// configured, in which case the leading prefix is consumed as the requested version instead.
 #[derive(Clone)]
-pub struct ApiVersionLayer<const N: usize, F> {
-    versions: [u16; N],
+pub struct ApiVersionLayer<F> {
+    routes: Routes,
     filter: F,
+    sources: Vec<VersionSource>,
+    discovery: Option<String>,
 }
 
-impl<const N: usize, F> ApiVersionLayer<N, F> {
-    /// Create a new [ApiVersionLayer].
+impl<F> ApiVersionLayer<F> {
+    /// Create a new [ApiVersionLayer] resolving flat `u16` versions by exact membership for all
+    /// paths.
     ///
     /// The given versions must not be empty and must be strictly monotonically increasing, e.g.
     /// `[0, 1, 2]`.
-    pub fn new(versions: [u16; N], filter: F) -> Result<Self, NewApiVersionLayerError> {
-        if versions.is_empty() {
-            return Err(NewApiVersionLayerError::Empty);
-        }
+    pub fn new<const N: usize>(
+        versions: [u16; N],
+        filter: F,
+    ) -> Result<Self, NewApiVersionLayerError> {
+        let versions = Versions::numeric(versions.to_vec())?;
+        Ok(Self {
+            routes: Routes::single(versions),
+            filter,
+            sources: vec![VersionSource::Header],
+            discovery: None,
+        })
+    }
+
+    /// Create a new [ApiVersionLayer] resolving [semver] versions by compatibility for all paths.
+    ///
+    /// The given versions must not be empty and must be strictly monotonically increasing, e.g.
+    /// `["1.0.0", "1.1.0", "2.0.0"]`. Clients select a version via any configured [VersionSource],
+    /// sending either a full version or a `VersionReq` such as `"^1.2"`; the highest supported
+    /// version satisfying the requirement is served. When no source yields a version the highest
+    /// overall version is used.
+    pub fn semver(versions: Vec<Version>, filter: F) -> Result<Self, NewApiVersionLayerError> {
+        let versions = Versions::semver(versions)?;
+        Ok(Self {
+            routes: Routes::single(versions),
+            filter,
+            sources: vec![VersionSource::Header],
+            discovery: None,
+        })
+    }
 
-        if versions.as_slice().windows(2).any(|w| w[0] >= w[1]) {
-            return Err(NewApiVersionLayerError::NotIncreasing);
+    /// Start building an [ApiVersionLayer] with per-route version ranges. Register routes via
+    /// [route](ApiVersionLayerBuilder::route) / [route_semver](ApiVersionLayerBuilder::route_semver)
+    /// and finish with [build](ApiVersionLayerBuilder::build).
+    pub fn builder(filter: F) -> ApiVersionLayerBuilder<F> {
+        ApiVersionLayerBuilder {
+            routes: Routes::default(),
+            filter,
+            sources: vec![VersionSource::Header],
         }
+    }
+
+    /// Replace the ordered list of [VersionSource]s the middleware consults to extract the
+    /// requested version. Sources are tried in the given precedence order; the first one yielding a
+    /// valid version wins. The default is `[VersionSource::Header]`.
+    pub fn with_sources(mut self, sources: Vec<VersionSource>) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    /// Enable the version-discovery endpoint at the given path, e.g. `"/versions"`. The endpoint is
+    /// intercepted before filtering and rewriting, is itself never version-rewritten, and returns a
+    /// JSON document describing the supported versions, the default (latest) version and any
+    /// deprecation/sunset metadata per route. Disabled by default.
+    pub fn with_discovery(mut self, path: impl Into<String>) -> Self {
+        self.discovery = Some(path.into());
+        self
+    }
+
+    /// Mark a supported version of a route as deprecated in the discovery document. The `version`
+    /// must be given in its displayed form, e.g. `"v1"` in numeric mode or `"1.0.0"` in semver
+    /// mode; `prefix` is `"/"` for layers created via [new](Self::new) / [semver](Self::semver).
+    pub fn deprecate(mut self, prefix: &str, version: &str) -> Self {
+        self.routes.meta_mut(prefix, version).deprecated = true;
+        self
+    }
+
+    /// Attach a sunset date to a supported version of a route in the discovery document. See
+    /// [deprecate](Self::deprecate) for the `prefix` and `version` conventions.
+    pub fn sunset(mut self, prefix: &str, version: &str, date: Date) -> Self {
+        self.routes.meta_mut(prefix, version).sunset = Some(date);
+        self
+    }
+}
+
+/// Builder for an [ApiVersionLayer] with per-route version ranges; see
+/// [ApiVersionLayer::builder].
+pub struct ApiVersionLayerBuilder<F> {
+    routes: Routes,
+    filter: F,
+    sources: Vec<VersionSource>,
+}
+
+impl<F> ApiVersionLayerBuilder<F> {
+    /// Register a flat `u16` version range for the given path prefix, e.g. `("/orders", 1..=3)`.
+    ///
+    /// The range must not be empty.
+    pub fn route(
+        mut self,
+        prefix: &str,
+        versions: RangeInclusive<u16>,
+    ) -> Result<Self, NewApiVersionLayerError> {
+        let versions = Versions::numeric(versions.collect())?;
+        self.routes.insert(prefix, versions);
+        Ok(self)
+    }
+
+    /// Register a [semver] version set for the given path prefix.
+    ///
+    /// The versions must not be empty and must be strictly monotonically increasing.
+    pub fn route_semver(
+        mut self,
+        prefix: &str,
+        versions: Vec<Version>,
+    ) -> Result<Self, NewApiVersionLayerError> {
+        let versions = Versions::semver(versions)?;
+        self.routes.insert(prefix, versions);
+        Ok(self)
+    }
+
+    /// Replace the ordered list of [VersionSource]s; see [ApiVersionLayer::with_sources].
+    pub fn with_sources(mut self, sources: Vec<VersionSource>) -> Self {
+        self.sources = sources;
+        self
+    }
 
-        Ok(Self { versions, filter })
+    /// Finish building the [ApiVersionLayer]. Use [ApiVersionLayer::with_discovery],
+    /// [deprecate](ApiVersionLayer::deprecate) and [sunset](ApiVersionLayer::sunset) on the result
+    /// to configure version discovery.
+    pub fn build(self) -> ApiVersionLayer<F> {
+        ApiVersionLayer {
+            routes: self.routes,
+            filter: self.filter,
+            sources: self.sources,
+            discovery: None,
+        }
     }
 }
 
-impl<const N: usize, S, F> Layer<S> for ApiVersionLayer<N, F>
+impl<S, F> Layer<S> for ApiVersionLayer<F>
 where
     F: ApiVersionFilter,
 {
-    type Service = ApiVersion<N, S, F>;
+    type Service = ApiVersion<S, F>;
 
     fn layer(&self, inner: S) -> Self::Service {
         ApiVersion {
             inner,
-            versions: self.versions,
+            routes: self.routes.clone(),
             filter: self.filter.clone(),
+            sources: self.sources.clone(),
+            discovery: self.discovery.clone(),
+        }
+    }
+}
+
+/// A prefix trie keyed on path segments, mapping path prefixes to their supported [Versions].
+#[derive(Clone, Debug, Default)]
+struct Routes {
+    node: Node,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Node {
+    versions: Option<Versions>,
+    meta: HashMap<String, VersionMeta>,
+    children: HashMap<String, Node>,
+}
+
+impl Routes {
+    /// A trie with a single entry applying the given versions to all paths.
+    fn single(versions: Versions) -> Self {
+        let mut routes = Routes::default();
+        routes.insert("/", versions);
+        routes
+    }
+
+    /// Register supported versions for a path prefix.
+    fn insert(&mut self, prefix: &str, versions: Versions) {
+        self.node_mut_at(prefix).versions = Some(versions);
+    }
+
+    /// Mutable access to the [VersionMeta] of a version at a path prefix, creating it if absent.
+    fn meta_mut(&mut self, prefix: &str, version: &str) -> &mut VersionMeta {
+        self.node_mut_at(prefix)
+            .meta
+            .entry(version.to_owned())
+            .or_default()
+    }
+
+    /// Mutable access to the node at a path prefix, creating intermediate nodes as needed.
+    fn node_mut_at(&mut self, prefix: &str) -> &mut Node {
+        let mut node = &mut self.node;
+        for segment in prefix.split('/').filter(|s| !s.is_empty()) {
+            node = node.children.entry(segment.to_owned()).or_default();
+        }
+        node
+    }
+
+    /// Build the discovery document describing every registered route, sorted by prefix.
+    fn discovery(&self) -> Vec<RouteDiscovery> {
+        fn collect(node: &Node, prefix: &str, out: &mut Vec<RouteDiscovery>) {
+            if let Some(versions) = &node.versions {
+                let display_prefix = if prefix.is_empty() { "/" } else { prefix };
+                out.push(RouteDiscovery {
+                    prefix: display_prefix.to_owned(),
+                    default: versions.latest_display(),
+                    versions: versions
+                        .list_display()
+                        .into_iter()
+                        .map(|version| VersionDiscovery {
+                            meta: node.meta.get(&version).cloned().unwrap_or_default(),
+                            version,
+                        })
+                        .collect(),
+                });
+            }
+            for (segment, child) in &node.children {
+                collect(child, &format!("{prefix}/{segment}"), out);
+            }
+        }
+
+        let mut out = Vec::new();
+        collect(&self.node, "", &mut out);
+        out.sort_by(|a, b| a.prefix.cmp(&b.prefix));
+        out
+    }
+
+    /// Longest-prefix-match the given path, returning the supported versions of the most specific
+    /// registered prefix, or `None` if no prefix matches.
+    fn match_path(&self, path: &str) -> Option<&Versions> {
+        let mut node = &self.node;
+        let mut best = node.versions.as_ref();
+        for segment in path.split('/').filter(|s| !s.is_empty()) {
+            match node.children.get(segment) {
+                Some(child) => {
+                    node = child;
+                    if node.versions.is_some() {
+                        best = node.versions.as_ref();
+                    }
+                }
+                None => break,
+            }
+        }
+        best
+    }
+}
+
+/// A place from which the requested API version can be extracted. Sources are consulted in the
+/// order configured on the [ApiVersionLayer]; the first one yielding a valid version wins.
+#[derive(Clone, Debug)]
+pub enum VersionSource {
+    /// The `"x-api-version"` header, e.g. `"v2"` or (in semver mode) `"^1.2"`.
+    Header,
+
+    /// A leading path segment, e.g. `"/v2/..."`. When configured, this segment is consumed rather
+    /// than rejected.
+    Path,
+
+    /// A vendor media type in the `"accept"` header, e.g. `"application/vnd.myapi.v2+json"`.
+    Accept,
+
+    /// The `"api-version"` query parameter, e.g. `"?api-version=2"`.
+    Query,
+}
+
+impl VersionSource {
+    /// Extract the raw version token from the request together with a flag indicating whether a
+    /// leading path version prefix was consumed.
+    fn extract(&self, request: &Request) -> Option<(String, bool)> {
+        match self {
+            VersionSource::Header => request
+                .headers()
+                .get(&X_API_VERSION)
+                .and_then(|v| v.to_str().ok())
+                .map(|s| (s.to_owned(), false)),
+
+            VersionSource::Path => PATH_VERSION
+                .captures(request.uri().path())
+                .and_then(|c| c.get(1))
+                .map(|m| (m.as_str().to_owned(), true)),
+
+            VersionSource::Accept => request
+                .headers()
+                .get(ACCEPT)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|s| {
+                    ACCEPT_VERSION
+                        .captures(s)
+                        .and_then(|c| c.get(1))
+                        .map(|m| m.as_str().to_owned())
+                })
+                .map(|t| (t, false)),
+
+            VersionSource::Query => request
+                .uri()
+                .query()
+                .and_then(|q| query_value(q, API_VERSION_QUERY))
+                .map(|v| (v, false)),
+        }
+    }
+}
+
+/// The set of supported versions together with the resolution strategy.
+#[derive(Clone, Debug)]
+enum Versions {
+    /// Flat `u16` versions, resolved by exact membership.
+    Numeric(Vec<u16>),
+
+    /// Semantic versions, resolved by compatibility.
+    Semver(Vec<Version>),
+}
+
+impl Versions {
+    /// Validate and build a flat `u16` version set.
+    fn numeric(versions: Vec<u16>) -> Result<Self, NewApiVersionLayerError> {
+        validate(versions.windows(2).any(|w| w[0] >= w[1]), versions.is_empty())?;
+        Ok(Versions::Numeric(versions))
+    }
+
+    /// Validate and build a [semver] version set.
+    fn semver(versions: Vec<Version>) -> Result<Self, NewApiVersionLayerError> {
+        validate(versions.windows(2).any(|w| w[0] >= w[1]), versions.is_empty())?;
+        Ok(Versions::Semver(versions))
+    }
+
+    /// The version prefixes (without leading `"v"`) a path must not start with. In numeric mode
+    /// these are the versions themselves, in semver mode the distinct major components.
+    fn prefixes(&self) -> Vec<u16> {
+        match self {
+            Versions::Numeric(versions) => versions.clone(),
+            Versions::Semver(versions) => {
+                let mut majors = versions.iter().map(|v| v.major as u16).collect::<Vec<_>>();
+                majors.dedup();
+                majors
+            }
+        }
+    }
+
+    /// Parse a raw version token into a [Requirement] according to the resolution mode, or `None`
+    /// if the token is not a valid version designator for this mode.
+    fn parse(&self, token: &str) -> Option<Requirement> {
+        let token = normalize(token);
+        match self {
+            Versions::Numeric(_) => token.parse().ok().map(Requirement::Numeric),
+            Versions::Semver(_) => VersionReq::parse(&token).ok().map(Requirement::Semver),
+        }
+    }
+
+    /// Resolve a [Requirement] into the major prefix and the precisely resolved version, or `None`
+    /// if no supported version satisfies it.
+    fn resolve(&self, requirement: &Requirement) -> Option<(u16, ResolvedVersion)> {
+        match (self, requirement) {
+            (Versions::Numeric(versions), Requirement::Numeric(v)) => versions
+                .contains(v)
+                .then(|| (*v, ResolvedVersion::Numeric(*v))),
+
+            (Versions::Semver(versions), Requirement::Semver(req)) => versions
+                .iter()
+                .rev()
+                .find(|v| req.matches(v))
+                .map(|v| (v.major as u16, ResolvedVersion::Semver(v.clone()))),
+
+            // A requirement is always parsed in the mode's own flavour, so the cross combinations
+            // cannot occur.
+            _ => None,
+        }
+    }
+
+    /// The highest supported version, used when no source yields a version.
+    fn latest(&self) -> (u16, ResolvedVersion) {
+        match self {
+            Versions::Numeric(versions) => {
+                let v = *versions.last().expect("versions is not empty");
+                (v, ResolvedVersion::Numeric(v))
+            }
+            Versions::Semver(versions) => {
+                let v = versions.last().expect("versions is not empty").clone();
+                (v.major as u16, ResolvedVersion::Semver(v))
+            }
+        }
+    }
+
+    /// A human readable, comma separated list of all supported versions, used in `404` responses.
+    fn supported(&self) -> String {
+        self.list_display().join(", ")
+    }
+
+    /// The displayed form of all supported versions, e.g. `["v0", "v1"]` or `["1.0.0", "2.0.0"]`.
+    fn list_display(&self) -> Vec<String> {
+        match self {
+            Versions::Numeric(versions) => versions.iter().map(|v| format!("v{v}")).collect(),
+            Versions::Semver(versions) => versions.iter().map(|v| v.to_string()).collect(),
+        }
+    }
+
+    /// The displayed form of the highest supported version.
+    fn latest_display(&self) -> String {
+        match self {
+            Versions::Numeric(versions) => {
+                format!("v{}", versions.last().expect("versions is not empty"))
+            }
+            Versions::Semver(versions) => {
+                versions.last().expect("versions is not empty").to_string()
+            }
+        }
+    }
+}
+
+/// Per-version deprecation metadata surfaced in the discovery document.
+#[derive(Clone, Debug, Default, Serialize)]
+struct VersionMeta {
+    /// Whether the version is deprecated.
+    #[serde(skip_serializing_if = "is_false")]
+    deprecated: bool,
+
+    /// The date on which the version is retired, if scheduled.
+    #[serde(skip_serializing_if = "Option::is_none", serialize_with = "serialize_sunset")]
+    sunset: Option<Date>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !*value
+}
+
+/// Serialize a sunset date as an ISO-8601 calendar date string (e.g. `"2025-01-01"`) so the
+/// discovery payload is deterministic regardless of the `time` crate's Serde feature flags, which
+/// would otherwise default to the compact `[year, ordinal]` form.
+fn serialize_sunset<S>(date: &Option<Date>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: serde::Serializer,
+{
+    match date {
+        Some(date) => serializer.serialize_str(&date.to_string()),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// The version-discovery document returned by the discovery endpoint.
+#[derive(Serialize)]
+struct Discovery {
+    routes: Vec<RouteDiscovery>,
+}
+
+/// The discovery entry for a single route.
+#[derive(Serialize)]
+struct RouteDiscovery {
+    prefix: String,
+    default: String,
+    versions: Vec<VersionDiscovery>,
+}
+
+/// The discovery entry for a single version of a route.
+#[derive(Serialize)]
+struct VersionDiscovery {
+    version: String,
+
+    #[serde(flatten)]
+    meta: VersionMeta,
+}
+
+/// Shared version-set validation.
+fn validate(not_increasing: bool, is_empty: bool) -> Result<(), NewApiVersionLayerError> {
+    if is_empty {
+        return Err(NewApiVersionLayerError::Empty);
+    }
+    if not_increasing {
+        return Err(NewApiVersionLayerError::NotIncreasing);
+    }
+    Ok(())
+}
+
+/// A parsed, mode-specific version requirement extracted from a request.
+#[derive(Clone, Debug)]
+enum Requirement {
+    /// An exact `u16` version.
+    Numeric(u16),
+
+    /// A semantic version requirement.
+    Semver(VersionReq),
+}
+
+/// The version resolved for a request, remembered in the request extensions so downstream services
+/// can recover it.
+#[derive(Clone, Debug)]
+pub enum ResolvedVersion {
+    /// A flat `u16` version.
+    Numeric(u16),
+
+    /// A semantic version.
+    Semver(Version),
+}
+
+impl ResolvedVersion {
+    /// Serialize this version into an `"x-api-version"` response header value. Numeric versions are
+    /// encoded via [XApiVersion::encode], semver versions via their canonical string form.
+    fn header_value(&self) -> HeaderValue {
+        match self {
+            ResolvedVersion::Numeric(v) => {
+                let mut values = Vec::new();
+                XApiVersion(*v).encode(&mut values);
+                values
+                    .into_iter()
+                    .next()
+                    .expect("XApiVersion encodes exactly one value")
+            }
+            ResolvedVersion::Semver(v) => {
+                HeaderValue::from_str(&v.to_string()).expect("version is a valid header value")
+            }
         }
     }
 }
@@ -91,9 +587,47 @@ where
 pub trait ApiVersionFilter: Clone + Send + 'static {
     /// Requests are only rewritten, if the given URI passes, i.e. results in `true`.
     fn filter(&self, uri: &Uri) -> impl Future<Output = bool> + Send;
+
+    /// If the filter is synchronous, return its result directly. Returning `Some` enables the
+    /// unboxed fast path in [ApiVersion], which avoids boxing a fresh future per request; the
+    /// middleware state is still cloned and the rewritten URI and response header values are still
+    /// allocated per request. The default `None` selects the boxed path driving
+    /// [filter](Self::filter). See [SyncApiVersionFilter].
+    fn filter_sync(&self, _uri: &Uri) -> Option<bool> {
+        None
+    }
 }
 
-/// [ApiVersionFilter] making all requests be rewritten.
+/// A synchronous, side-effect-free [ApiVersionFilter].
+///
+/// Wrapping an implementation of this in [SyncFilter] lets [ApiVersion] resolve the version and
+/// run the downstream service without the per-request `Box::pin`
+/// allocation that the async filter path requires: the whole middleware body is synchronous once
+/// the filter decision is known, so only the downstream future needs to be driven.
+pub trait SyncApiVersionFilter: Clone + Send + Sync + 'static {
+    /// Requests are only rewritten, if the given URI passes, i.e. results in `true`.
+    fn filter(&self, uri: &Uri) -> bool;
+}
+
+/// Adapter turning a [SyncApiVersionFilter] into an [ApiVersionFilter] that takes the unboxed
+/// fast path.
+#[derive(Clone, Copy)]
+pub struct SyncFilter<T>(pub T);
+
+impl<T> ApiVersionFilter for SyncFilter<T>
+where
+    T: SyncApiVersionFilter,
+{
+    async fn filter(&self, uri: &Uri) -> bool {
+        self.0.filter(uri)
+    }
+
+    fn filter_sync(&self, uri: &Uri) -> Option<bool> {
+        Some(self.0.filter(uri))
+    }
+}
+
+/// [ApiVersionFilter] / [SyncApiVersionFilter] making all requests be rewritten.
 #[derive(Clone, Copy)]
 pub struct All;
 
@@ -101,6 +635,16 @@ impl ApiVersionFilter for All {
     async fn filter(&self, _uri: &Uri) -> bool {
         true
     }
+
+    fn filter_sync(&self, _uri: &Uri) -> Option<bool> {
+        Some(true)
+    }
+}
+
+impl SyncApiVersionFilter for All {
+    fn filter(&self, _uri: &Uri) -> bool {
+        true
+    }
 }
 
 /// Error creating an [ApiVersionLayer].
@@ -115,13 +659,15 @@ pub enum NewApiVersionLayerError {
 
 /// See [ApiVersionLayer].
 #[derive(Clone)]
-pub struct ApiVersion<const N: usize, S, F> {
+pub struct ApiVersion<S, F> {
     inner: S,
-    versions: [u16; N],
+    routes: Routes,
     filter: F,
+    sources: Vec<VersionSource>,
+    discovery: Option<String>,
 }
 
-impl<const N: usize, S, F> Service<Request> for ApiVersion<N, S, F>
+impl<S, F> Service<Request> for ApiVersion<S, F>
 where
     S: Service<Request, Response = Response> + Clone + Send + 'static,
     S::Future: Send + 'static,
@@ -129,80 +675,363 @@ where
 {
     type Response = S::Response;
     type Error = S::Error;
-    type Future = BoxFuture<'static, Result<Self::Response, Self::Error>>;
+    type Future = ApiVersionFuture<S::Future, S::Error>;
 
     fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
         self.inner.poll_ready(cx)
     }
 
     fn call(&mut self, mut request: Request) -> Self::Future {
+        // Intercept the version-discovery endpoint before filtering and rewriting, avoiding the
+        // per-request clones on that path.
+        if self.discovery.as_deref() == Some(request.uri().path()) {
+            return ApiVersionFuture::ready(discovery_response(&self.routes));
+        }
+
         let mut inner = self.inner.clone();
-        let versions = self.versions;
+        let routes = self.routes.clone();
         let filter = self.filter.clone();
+        let sources = self.sources.clone();
 
-        Box::pin(async move {
-            // Always serve "/", typically used as readiness probe, unmodified.
-            if request.uri().path() == "/" {
-                return inner.call(request).await;
-            }
-
-            // Do not allow the path to start with one of the valid version prefixes.
-            if versions
-                .iter()
-                .any(|version| request.uri().path().starts_with(&format!("/v{version}")))
-            {
-                let response = (
-                    StatusCode::BAD_REQUEST,
-                    "path must not start with version prefix like '/v0'",
-                );
-                return Ok(response.into_response());
+        // Fast path: a synchronous filter lets the entire middleware body run inline without
+        // boxing a future, so only the downstream future is driven. The clones above and the URI
+        // rewrite still allocate.
+        if let Some(passed) = filter.filter_sync(request.uri()) {
+            if !passed {
+                return ApiVersionFuture::passthrough(inner.call(request));
             }
+            return match process(&routes, &sources, &mut request) {
+                Outcome::Early(response) => ApiVersionFuture::ready(response),
+                Outcome::Passthrough => ApiVersionFuture::passthrough(inner.call(request)),
+                Outcome::Rewrite(headers) => {
+                    ApiVersionFuture::rewrite(inner.call(request), headers)
+                }
+            };
+        }
 
+        // Slow path: the filter is asynchronous, so the body must be boxed.
+        ApiVersionFuture::boxed(Box::pin(async move {
             if !filter.filter(request.uri()).await {
                 return inner.call(request).await;
             }
+            match process(&routes, &sources, &mut request) {
+                Outcome::Early(response) => Ok(response),
+                Outcome::Passthrough => inner.call(request).await,
+                Outcome::Rewrite(headers) => {
+                    let mut response = inner.call(request).await?;
+                    headers.apply(&mut response);
+                    Ok(response)
+                }
+            }
+        }))
+    }
+}
+
+/// Build the JSON version-discovery response for the given routes.
+fn discovery_response(routes: &Routes) -> Response {
+    Json(Discovery {
+        routes: routes.discovery(),
+    })
+    .into_response()
+}
+
+/// What the (synchronous) version negotiation determined should happen to a request.
+enum Outcome {
+    /// Answer directly with the given response (`400`/`404`).
+    Early(Response),
+
+    /// Run the downstream service on the unmodified request (readiness probe, unmatched route).
+    Passthrough,
+
+    /// The request URI was rewritten in place; run the downstream service and add the given version
+    /// headers to its response.
+    Rewrite(ResponseHeaders),
+}
+
+/// The version headers added to a rewritten response. Carried by [ApiVersionFuture].
+struct ResponseHeaders {
+    version: HeaderValue,
+    supported: HeaderValue,
+    warn: bool,
+}
+
+impl ResponseHeaders {
+    /// Add the version headers to the given response.
+    fn apply(self, response: &mut Response) {
+        let headers = response.headers_mut();
+        headers.insert(&X_API_VERSION, self.version);
+        headers.insert(&X_API_SUPPORTED_VERSIONS, self.supported);
+        if self.warn {
+            headers.insert(&X_API_VERSION_WARNING, IMPLICIT_VERSION_WARNING.clone());
+        }
+    }
+}
+
+/// Synchronously negotiate the API version for a request, rewriting its URI in place when
+/// applicable. This is the shared core of both the sync and async filter paths.
+fn process(routes: &Routes, sources: &[VersionSource], request: &mut Request) -> Outcome {
+    // Always serve "/", typically used as readiness probe, unmodified.
+    if request.uri().path() == "/" {
+        return Outcome::Passthrough;
+    }
 
-            // Determine API version.
-            let version = request.extract_parts::<TypedHeader<XApiVersion>>().await;
-            let version = version
-                .as_ref()
-                .map(|TypedHeader(XApiVersion(v))| v)
-                .unwrap_or_else(|_| versions.last().expect("versions is not empty"));
-            if !versions.contains(version) {
+    // Longest-prefix-match the path to the applicable version range, ignoring a leading path
+    // version prefix when a path source is configured. Requests not matching any registered route
+    // are passed through unmodified.
+    let path_source = sources.iter().any(|s| matches!(s, VersionSource::Path));
+    let routing_path = if path_source {
+        strip_version_prefix(request.uri().path())
+    } else {
+        request.uri().path().to_owned()
+    };
+    let versions = match routes.match_path(&routing_path) {
+        Some(versions) => versions,
+        None => return Outcome::Passthrough,
+    };
+
+    // Do not allow the path to start with one of the valid version prefixes, unless a path source
+    // is configured which deliberately consumes the prefix.
+    if !path_source
+        && versions
+            .prefixes()
+            .iter()
+            .any(|prefix| request.uri().path().starts_with(&format!("/v{prefix}")))
+    {
+        let response = (
+            StatusCode::BAD_REQUEST,
+            "path must not start with version prefix like '/v0'",
+        );
+        return Outcome::Early(response.into_response());
+    }
+
+    // Try each configured source in precedence order; the first one yielding a parseable
+    // requirement wins.
+    let candidate = sources.iter().find_map(|source| {
+        source
+            .extract(request)
+            .and_then(|(token, is_path)| versions.parse(&token).map(|req| (req, is_path)))
+    });
+
+    // Determine API version, the major prefix to prepend and whether the version was selected
+    // implicitly.
+    let (prefix, resolved, defaulted) = match candidate {
+        Some((requirement, _)) => match versions.resolve(&requirement) {
+            Some((prefix, resolved)) => (prefix, resolved, false),
+            None => {
                 let response = (
                     StatusCode::NOT_FOUND,
-                    format!("unknown version '{version}'"),
+                    format!(
+                        "no supported version satisfies the request, supported versions: {}",
+                        versions.supported()
+                    ),
                 );
-                return Ok(response.into_response());
+                return Outcome::Early(response.into_response());
             }
-            debug!(?version, "using API version");
-
-            // Prepend the suitable prefix to the request URI.
-            let mut parts = request.uri().to_owned().into_parts();
-            let paq = parts.path_and_query.expect("uri has 'path and query'");
-            let mut paq_parts = paq.as_str().split('?');
-            let path = paq_parts.next().expect("uri has path");
-            let paq = match paq_parts.next() {
-                Some(query) => format!("/v{version}{path}?{query}"),
-                None => format!("/v{version}{path}"),
-            };
-            let paq = PathAndQuery::from_maybe_shared(paq).expect("new 'path and query' is valid");
-            parts.path_and_query = Some(paq);
-            let uri = Uri::from_parts(parts).expect("parts are valid");
+        },
+        None => {
+            let (prefix, resolved) = versions.latest();
+            (prefix, resolved, true)
+        }
+    };
+    debug!(?resolved, "using API version");
 
-            // Rewrite the request URI and run the downstream services.
-            request.uri_mut().clone_from(&uri);
-            inner.call(request).await
-        })
+    // Serialize the negotiated version and supported set for the response before `resolved` is
+    // moved into the request extensions.
+    let headers = ResponseHeaders {
+        version: resolved.header_value(),
+        supported: HeaderValue::from_str(&versions.supported())
+            .expect("supported versions are a valid header value"),
+        warn: defaulted,
+    };
+
+    // Remember the precisely resolved version for downstream services.
+    request.extensions_mut().insert(resolved);
+
+    // Prepend the suitable prefix to the request URI, consuming any leading path version prefix
+    // first. The prefix is stripped whenever a path source is configured (matching how
+    // `routing_path` was computed above), not only when the path source was the one that won, so
+    // the URI is never double-prefixed when a higher-precedence source selects the version.
+    let mut parts = request.uri().to_owned().into_parts();
+    let paq = parts.path_and_query.expect("uri has 'path and query'");
+    let mut paq_parts = paq.as_str().split('?');
+    let path = paq_parts.next().expect("uri has path");
+    let path = if path_source {
+        strip_version_prefix(path)
+    } else {
+        path.to_owned()
+    };
+    let paq = match paq_parts.next() {
+        Some(query) => format!("/v{prefix}{path}?{query}"),
+        None => format!("/v{prefix}{path}"),
+    };
+    let paq = PathAndQuery::from_maybe_shared(paq).expect("new 'path and query' is valid");
+    parts.path_and_query = Some(paq);
+    let uri = Uri::from_parts(parts).expect("parts are valid");
+    request.uri_mut().clone_from(&uri);
+
+    Outcome::Rewrite(headers)
+}
+
+pin_project! {
+    /// Future returned by [ApiVersion]. On the synchronous filter fast path it drives the
+    /// downstream future directly (without boxing); on the asynchronous filter path it falls back
+    /// to a boxed future. See [SyncApiVersionFilter]. Its internal states are opaque so they cannot
+    /// be constructed outside this crate.
+    pub struct ApiVersionFuture<Fut, E> {
+        #[pin]
+        inner: ApiVersionFutureInner<Fut, E>,
+    }
+}
+
+pin_project! {
+    #[project = ApiVersionFutureProj]
+    enum ApiVersionFutureInner<Fut, E> {
+        /// An early response (`400`/`404` or the discovery document) produced without touching
+        /// the downstream service.
+        Ready { response: Option<Response> },
+
+        /// The downstream future, run on an unmodified request.
+        Passthrough { #[pin] future: Fut },
+
+        /// The downstream future whose response gets version headers added.
+        Rewrite {
+            #[pin]
+            future: Fut,
+            headers: Option<ResponseHeaders>,
+        },
+
+        /// The boxed asynchronous-filter path.
+        Boxed {
+            #[pin]
+            future: BoxFuture<'static, Result<Response, E>>,
+        },
+    }
+}
+
+impl<Fut, E> ApiVersionFuture<Fut, E> {
+    fn ready(response: Response) -> Self {
+        Self {
+            inner: ApiVersionFutureInner::Ready {
+                response: Some(response),
+            },
+        }
+    }
+
+    fn passthrough(future: Fut) -> Self {
+        Self {
+            inner: ApiVersionFutureInner::Passthrough { future },
+        }
+    }
+
+    fn rewrite(future: Fut, headers: ResponseHeaders) -> Self {
+        Self {
+            inner: ApiVersionFutureInner::Rewrite {
+                future,
+                headers: Some(headers),
+            },
+        }
+    }
+
+    fn boxed(future: BoxFuture<'static, Result<Response, E>>) -> Self {
+        Self {
+            inner: ApiVersionFutureInner::Boxed { future },
+        }
+    }
+}
+
+impl<Fut, E> Future for ApiVersionFuture<Fut, E>
+where
+    Fut: Future<Output = Result<Response, E>>,
+{
+    type Output = Result<Response, E>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        match self.project().inner.project() {
+            ApiVersionFutureProj::Ready { response } => {
+                Poll::Ready(Ok(response.take().expect("future polled after completion")))
+            }
+            ApiVersionFutureProj::Passthrough { future } => future.poll(cx),
+            ApiVersionFutureProj::Rewrite { future, headers } => match future.poll(cx) {
+                Poll::Ready(Ok(mut response)) => {
+                    if let Some(headers) = headers.take() {
+                        headers.apply(&mut response);
+                    }
+                    Poll::Ready(Ok(response))
+                }
+                other => other,
+            },
+            ApiVersionFutureProj::Boxed { future } => future.poll(cx),
+        }
+    }
+}
+
+/// Name of the query parameter read by [VersionSource::Query].
+const API_VERSION_QUERY: &str = "api-version";
+
+/// Return the first value of the given key in a URL query string.
+fn query_value(query: &str, key: &str) -> Option<String> {
+    query.split('&').find_map(|pair| {
+        let mut kv = pair.splitn(2, '=');
+        (kv.next() == Some(key)).then(|| kv.next().unwrap_or("").to_owned())
+    })
+}
+
+/// Strip a leading path version prefix like `"/v2"`, yielding a path that always starts with `"/"`.
+fn strip_version_prefix(path: &str) -> String {
+    let stripped = PATH_PREFIX.replace(path, "$1");
+    if stripped.is_empty() {
+        "/".to_owned()
+    } else {
+        stripped.into_owned()
+    }
+}
+
+/// Strip a leading `"v"` from a version token if it is directly followed by a digit, so that path,
+/// query and `"accept"` tokens (`"2"`) and header tokens (`"v2"`) are handled uniformly while
+/// leaving semver requirements such as `"^1.2"` untouched.
+fn normalize(token: &str) -> String {
+    match token.strip_prefix('v') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_digit()) => rest.to_owned(),
+        _ => token.to_owned(),
     }
 }
 
 /// Header name for the [XApiVersion] custom HTTP header.
 pub static X_API_VERSION: HeaderName = HeaderName::from_static("x-api-version");
 
+/// Response header name listing the full set of supported versions.
+pub static X_API_SUPPORTED_VERSIONS: HeaderName =
+    HeaderName::from_static("x-api-supported-versions");
+
+/// Response header name set when the served version was selected implicitly, i.e. no configured
+/// source yielded a usable version.
+pub static X_API_VERSION_WARNING: HeaderName = HeaderName::from_static("x-api-version-warning");
+
+/// Value of the [X_API_VERSION_WARNING] header.
+static IMPLICIT_VERSION_WARNING: LazyLock<HeaderValue> = LazyLock::new(|| {
+    HeaderValue::from_static("implicit version selection; pin 'x-api-version' explicitly")
+});
+
 static VERSION: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r#"^v(0|[1-9][0-9]?)$"#).expect("version regex is valid"));
 
+/// Matches a leading path version segment like `"/v2"` or `"/v2/..."`, capturing the number.
+static PATH_VERSION: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^/v(0|[1-9][0-9]?)(?:/|$)"#).expect("path version regex is valid")
+});
+
+/// Matches a leading path version segment like `"/v2"` or `"/v2/..."` for stripping, capturing the
+/// trailing boundary (`"/"` or end of path) so only genuine version segments are consumed and the
+/// following slash is preserved, e.g. `"/v2/orders"` strips to `"/orders"` but `"/v2x"` is left
+/// untouched.
+static PATH_PREFIX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r#"^/v(?:0|[1-9][0-9]?)(/|$)"#).expect("path prefix regex is valid")
+});
+
+/// Matches the `vN` token in a vendor media type like `"application/vnd.myapi.v2+json"`.
+static ACCEPT_VERSION: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#"\.v(0|[1-9][0-9]?)\+"#).expect("accept version regex is valid"));
+
 /// Custom HTTP header conveying the API version, which is expected to be a version designator
 /// starting with `'v'` followed by a number from 0..+99 without leading zero, e.g. `v0`.
 #[derive(Debug)]
@@ -227,8 +1056,9 @@ impl Header for XApiVersion {
             .ok_or_else(headers::Error::invalid)
     }
 
-    fn encode<E: Extend<HeaderValue>>(&self, _values: &mut E) {
-        // We do not yet need to encode this header.
-        unimplemented!("not yet needed");
+    fn encode<E: Extend<HeaderValue>>(&self, values: &mut E) {
+        let value = HeaderValue::from_str(&format!("v{}", self.0))
+            .expect("version designator is a valid header value");
+        values.extend(std::iter::once(value));
     }
 }
@@ -1,4 +1,7 @@
-use api_version::{ApiVersionFilter, ApiVersionLayer, X_API_VERSION};
+use api_version::{
+    ApiVersionFilter, ApiVersionLayer, X_API_SUPPORTED_VERSIONS, X_API_VERSION,
+    X_API_VERSION_WARNING,
+};
 use axum::{
     body::Body,
     http::{Request, StatusCode, Uri},
@@ -33,11 +36,19 @@ async fn test() {
         .unwrap();
     let response = app.call(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    // The readiness path is not rewritten, so it carries no version headers.
+    assert!(!response.headers().contains_key(&X_API_VERSION));
+    assert!(!response.headers().contains_key(&X_API_SUPPORTED_VERSIONS));
 
     // No version.
     let request = Request::builder().uri("/test").body(Body::empty()).unwrap();
     let response = app.call(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    // The negotiated version and supported set are echoed back, with a warning for the implicit
+    // selection.
+    assert_eq!(response.headers()[&X_API_VERSION], "v1");
+    assert_eq!(response.headers()[&X_API_SUPPORTED_VERSIONS], "v0, v1");
+    assert!(response.headers().contains_key(&X_API_VERSION_WARNING));
     assert_eq!(text(response).await, "1");
 
     // Existing version.
@@ -48,6 +59,9 @@ async fn test() {
         .unwrap();
     let response = app.call(request).await.unwrap();
     assert_eq!(response.status(), StatusCode::OK);
+    // The explicitly requested version is echoed back without a warning.
+    assert_eq!(response.headers()[&X_API_VERSION], "v0");
+    assert!(!response.headers().contains_key(&X_API_VERSION_WARNING));
     assert_eq!(text(response).await, "0");
 
     // Another existing version.
@@ -79,6 +93,196 @@ async fn test() {
     assert_eq!(response.status(), StatusCode::BAD_REQUEST);
 }
 
+#[tokio::test]
+async fn test_version_sources() {
+    use api_version::{All, VersionSource};
+
+    let app = Router::new()
+        .route("/v0/test", get(ok_0))
+        .route("/v1/test", get(ok_1))
+        .route("/v2/test", get(ok_2));
+    let mut app = ApiVersionLayer::new([0, 1, 2], All)
+        .unwrap()
+        .with_sources(vec![
+            VersionSource::Path,
+            VersionSource::Query,
+            VersionSource::Accept,
+            VersionSource::Header,
+        ])
+        .layer(app);
+
+    // Leading path segment: consumed and re-applied.
+    let request = Request::builder()
+        .uri("/v2/test")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(text(response).await, "2");
+
+    // Query parameter.
+    let request = Request::builder()
+        .uri("/test?api-version=1")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(text(response).await, "1");
+
+    // Vendor media type in the accept header.
+    let request = Request::builder()
+        .uri("/test")
+        .header("accept", "application/vnd.myapi.v0+json")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(text(response).await, "0");
+
+    // Header still works and the highest precedence source (path) wins over it.
+    let request = Request::builder()
+        .uri("/v1/test")
+        .header(&X_API_VERSION, "v2")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(text(response).await, "1");
+
+    // No source yields a version: fall back to the latest.
+    let request = Request::builder().uri("/test").body(Body::empty()).unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(text(response).await, "2");
+}
+
+#[tokio::test]
+async fn test_version_sources_higher_precedence_over_path() {
+    use api_version::{All, VersionSource};
+
+    let app = Router::new()
+        .route("/v0/test", get(ok_0))
+        .route("/v1/test", get(ok_1))
+        .route("/v2/test", get(ok_2));
+    let mut app = ApiVersionLayer::new([0, 1, 2], All)
+        .unwrap()
+        .with_sources(vec![VersionSource::Header, VersionSource::Path])
+        .layer(app);
+
+    // A higher-precedence source (header) wins over the leading path segment, yet the `/v1`
+    // prefix is still stripped so the URI is rewritten to `/v2/test` rather than `/v2/v1/test`.
+    let request = Request::builder()
+        .uri("/v1/test")
+        .header(&X_API_VERSION, "v2")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[&X_API_VERSION], "v2");
+    assert_eq!(text(response).await, "2");
+}
+
+#[tokio::test]
+async fn test_per_route_versions() {
+    use api_version::All;
+
+    let app = Router::new()
+        .route("/v1/orders", get(ok_orders))
+        .route("/v2/orders", get(ok_orders))
+        .route("/v3/orders", get(ok_orders))
+        .route("/v2/reports", get(ok_reports))
+        .route("/v3/reports", get(ok_reports))
+        .route("/v4/reports", get(ok_reports));
+    let mut app = ApiVersionLayer::builder(All)
+        .route("/orders", 1..=3)
+        .unwrap()
+        .route("/reports", 2..=4)
+        .unwrap()
+        .build()
+        .layer(app);
+
+    // Orders default to their latest supported version.
+    let request = Request::builder()
+        .uri("/orders")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[&X_API_VERSION], "v3");
+    assert_eq!(text(response).await, "orders");
+
+    // A version supported by orders but requested on reports is out of range.
+    let request = Request::builder()
+        .uri("/reports")
+        .header(&X_API_VERSION, "v1")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // Reports serve their own range.
+    let request = Request::builder()
+        .uri("/reports")
+        .header(&X_API_VERSION, "v4")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()[&X_API_VERSION], "v4");
+    assert_eq!(text(response).await, "reports");
+
+    // Unregistered paths are passed through unmodified.
+    let request = Request::builder().uri("/health").body(Body::empty()).unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    assert!(!response.headers().contains_key(&X_API_VERSION));
+}
+
+#[tokio::test]
+async fn test_discovery() {
+    use api_version::All;
+    use time::macros::date;
+
+    let app = Router::new().route("/v1/orders", get(ok_orders));
+    let mut app = ApiVersionLayer::builder(All)
+        .route("/orders", 1..=3)
+        .unwrap()
+        .route("/reports", 2..=4)
+        .unwrap()
+        .build()
+        .with_discovery("/versions")
+        .deprecate("/orders", "v1")
+        .sunset("/orders", "v1", date!(2025 - 01 - 01))
+        .layer(app);
+
+    let request = Request::builder()
+        .uri("/versions")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.call(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    // The discovery endpoint is not version-rewritten, so it carries no version headers.
+    assert!(!response.headers().contains_key(&X_API_VERSION));
+
+    let body = text(response).await;
+    let doc: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let routes = doc["routes"].as_array().unwrap();
+    assert_eq!(routes.len(), 2);
+
+    let orders = &routes[0];
+    assert_eq!(orders["prefix"], "/orders");
+    assert_eq!(orders["default"], "v3");
+    let orders_versions = orders["versions"].as_array().unwrap();
+    assert_eq!(orders_versions[0]["version"], "v1");
+    assert_eq!(orders_versions[0]["deprecated"], true);
+    assert_eq!(orders_versions[0]["sunset"], "2025-01-01");
+    // Non-deprecated versions omit the metadata fields.
+    assert!(orders_versions[1].get("deprecated").is_none());
+
+    assert_eq!(routes[1]["prefix"], "/reports");
+    assert_eq!(routes[1]["default"], "v4");
+}
+
 #[derive(Clone)]
 struct FooFilter;
 
@@ -96,10 +300,22 @@ async fn ok_1() -> impl IntoResponse {
     "1"
 }
 
+async fn ok_2() -> impl IntoResponse {
+    "2"
+}
+
 async fn ok_foo() -> impl IntoResponse {
     "foo"
 }
 
+async fn ok_orders() -> impl IntoResponse {
+    "orders"
+}
+
+async fn ok_reports() -> impl IntoResponse {
+    "reports"
+}
+
 async fn text(response: Response) -> String {
     let text = response
         .into_body()